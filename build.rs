@@ -0,0 +1,47 @@
+//! Detects the compiler version and channel this crate is being built with,
+//! so the `since`/`before`/`nightly`/`beta` selectors in `src/selector.rs`
+//! can be decided without the proc-macro itself having to guess - both are
+//! genuinely host properties (the same `rustc` compiles this crate and
+//! whatever depends on it), unlike the target-only `cfg`s in `src/defer.rs`.
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = Command::new(rustc).args(["--version", "--verbose"]).output();
+
+    let verbose = match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        _ => String::new(),
+    };
+
+    if let Some(minor) = parse_minor(&verbose) {
+        println!("cargo:rustc-env=DAYWALKER_MINOR={minor}");
+    }
+    println!("cargo:rustc-env=DAYWALKER_CHANNEL={}", parse_channel(&verbose));
+}
+
+/// Pulls the minor version number out of the `release: 1.75.0` (or
+/// `1.76.0-nightly`) line of `rustc --version --verbose`.
+fn parse_minor(verbose: &str) -> Option<u32> {
+    let release = verbose.lines().find_map(|line| line.strip_prefix("release: "))?;
+    release.split('-').next()?.split('.').nth(1)?.parse().ok()
+}
+
+/// Classifies the channel from the same `release:` line. A `-dev` suffix
+/// (a rustc built from a local checkout) is treated the same as `-nightly`.
+fn parse_channel(verbose: &str) -> &'static str {
+    let release = match verbose.lines().find_map(|line| line.strip_prefix("release: ")) {
+        Some(release) => release,
+        None => return "stable",
+    };
+
+    if release.ends_with("-nightly") || release.ends_with("-dev") {
+        "nightly"
+    } else if release.contains("-beta") {
+        "beta"
+    } else {
+        "stable"
+    }
+}
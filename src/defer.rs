@@ -0,0 +1,129 @@
+//! Real, deferred `#[cfg(...)]` gating for explicit predicates.
+//!
+//! A bare `++[...]`/`--[...]` (no predicate) is decided immediately inside
+//! the proc-macro, which is the only option for payloads spliced into a
+//! bare-token position (e.g. the lone `const` in `++[const] fn foo()`). But
+//! an explicit predicate like `++(cfg(target_os = "linux"))[...]` names a
+//! real compilation target, which this proc-macro - always built for the
+//! host - has no reliable way to observe itself. So instead of guessing, we
+//! re-emit the payload behind a genuine `#[cfg(...)]`/`#[cfg(not(...))]`
+//! pair and let rustc's normal conditional compilation decide, the same way
+//! it would for hand-written code. The `?~[...][...]` then/else branch
+//! operator uses the same trick, just with both arms filled in instead of
+//! one of them being empty.
+//!
+//! The whole `#[cfg(...)] macro_rules! ... name!{}` sequence is wrapped in
+//! a `{ ... }` block so it's a single expression - without it, splicing a
+//! sequence of items followed by a macro call anywhere an *expression* is
+//! expected (e.g. `let x = ++(feature = "x")[1];`) doesn't parse, since an
+//! expression position needs exactly one expression, not an item sequence.
+//! That means an explicit predicate only works in a statement or full
+//! expression position now, not bare at item/module level - a block
+//! expression isn't itself a legal item - unlike the default predicate and
+//! the rustc selectors, which splice their payload directly and so still
+//! work anywhere, bare-token positions included. It also rules out
+//! expression-*list* positions like a macro argument list or an
+//! array/tuple literal: the excluded arm is still a `{ ... }` block that
+//! evaluates to `()`, which won't unify with the type of the other
+//! elements there.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use proc_macro2::{Delimiter, Group, Ident, Punct, Spacing, Span, TokenStream, TokenTree};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Wraps `payload` so it is included when `meta` holds and `positive` is
+/// `true` (i.e. a `++` sigil), or when `meta` does not hold and `positive`
+/// is `false` (a `--` sigil).
+pub(crate) fn gate(positive: bool, meta: TokenStream, payload: TokenStream) -> TokenStream {
+    if positive {
+        branch(meta, payload, TokenStream::new())
+    } else {
+        branch(meta, TokenStream::new(), payload)
+    }
+}
+
+/// Wraps `then_payload`/`else_payload` so exactly one is included, depending
+/// on whether `meta` holds - the deferred counterpart to a `?~[...][...]`
+/// branch operator pair, the same way [`gate`] is to a single `++`/`--`
+/// payload. The result is a single `{ ... }` block expression (see the
+/// module docs), so it's valid anywhere a statement or expression is
+/// expected.
+pub(crate) fn branch(meta: TokenStream, then_payload: TokenStream, else_payload: TokenStream) -> TokenStream {
+    let name = Ident::new(
+        &format!("__daywalker_cond_{}", COUNTER.fetch_add(1, Ordering::Relaxed)),
+        Span::call_site(),
+    );
+
+    let mut inner = TokenStream::new();
+    inner.extend(cfg_attr(meta.clone()));
+    inner.extend(macro_def(&name, then_payload));
+    inner.extend(cfg_attr(negate(meta)));
+    inner.extend(macro_def(&name, else_payload));
+    inner.extend(macro_call(&name));
+
+    TokenStream::from(TokenTree::Group(Group::new(Delimiter::Brace, inner)))
+}
+
+/// Builds `not(meta)`, for [`cfg_attr`] to wrap.
+fn negate(meta: TokenStream) -> TokenStream {
+    let mut inner = TokenStream::new();
+    inner.extend(TokenStream::from(TokenTree::Ident(Ident::new("not", Span::call_site()))));
+    inner.extend(TokenStream::from(TokenTree::Group(Group::new(Delimiter::Parenthesis, meta))));
+    inner
+}
+
+/// Builds `#[cfg(meta)]`.
+fn cfg_attr(meta: TokenStream) -> TokenStream {
+    let mut cfg_call = TokenStream::from(TokenTree::Ident(Ident::new("cfg", Span::call_site())));
+    cfg_call.extend(TokenStream::from(TokenTree::Group(Group::new(
+        Delimiter::Parenthesis,
+        meta,
+    ))));
+
+    let mut pound = Punct::new('#', Spacing::Alone);
+    pound.set_span(Span::call_site());
+
+    let mut out = TokenStream::from(TokenTree::Punct(pound));
+    out.extend(TokenStream::from(TokenTree::Group(Group::new(
+        Delimiter::Bracket,
+        cfg_call,
+    ))));
+    out
+}
+
+/// Builds `macro_rules! name { () => { body } }`.
+fn macro_def(name: &Ident, body: TokenStream) -> TokenStream {
+    let mut arm = TokenStream::from(TokenTree::Group(Group::new(Delimiter::Parenthesis, TokenStream::new())));
+    let mut fat_arrow = TokenStream::new();
+    let mut eq = Punct::new('=', Spacing::Joint);
+    eq.set_span(Span::call_site());
+    let mut gt = Punct::new('>', Spacing::Alone);
+    gt.set_span(Span::call_site());
+    fat_arrow.extend(TokenStream::from(TokenTree::Punct(eq)));
+    fat_arrow.extend(TokenStream::from(TokenTree::Punct(gt)));
+    arm.extend(fat_arrow);
+    arm.extend(TokenStream::from(TokenTree::Group(Group::new(Delimiter::Brace, body))));
+
+    let mut out = TokenStream::from(TokenTree::Ident(Ident::new("macro_rules", Span::call_site())));
+    let mut bang = Punct::new('!', Spacing::Alone);
+    bang.set_span(Span::call_site());
+    out.extend(TokenStream::from(TokenTree::Punct(bang)));
+    out.extend(TokenStream::from(TokenTree::Ident(name.clone())));
+    out.extend(TokenStream::from(TokenTree::Group(Group::new(Delimiter::Brace, arm))));
+    out
+}
+
+/// Builds `name!{}`.
+fn macro_call(name: &Ident) -> TokenStream {
+    let mut out = TokenStream::from(TokenTree::Ident(name.clone()));
+    let mut bang = Punct::new('!', Spacing::Alone);
+    bang.set_span(Span::call_site());
+    out.extend(TokenStream::from(TokenTree::Punct(bang)));
+    out.extend(TokenStream::from(TokenTree::Group(Group::new(
+        Delimiter::Brace,
+        TokenStream::new(),
+    ))));
+    out
+}
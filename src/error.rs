@@ -0,0 +1,25 @@
+//! Builds a `compile_error!{ "..." }` token tree carrying a caller-given
+//! span, so malformed conditional syntax is reported as a precise
+//! diagnostic instead of being silently passed through or producing a
+//! confusing downstream failure.
+
+use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+/// Builds `compile_error!{ "message" }`, spanned at `span`.
+pub(crate) fn emit(span: Span, message: &str) -> TokenStream {
+    let name = Ident::new("compile_error", span);
+
+    let mut bang = Punct::new('!', Spacing::Alone);
+    bang.set_span(span);
+
+    let mut lit = Literal::string(message);
+    lit.set_span(span);
+
+    let mut group = Group::new(Delimiter::Brace, TokenStream::from(TokenTree::Literal(lit)));
+    group.set_span(span);
+
+    let mut out = TokenStream::from(TokenTree::Ident(name));
+    out.extend(TokenStream::from(TokenTree::Punct(bang)));
+    out.extend(TokenStream::from(TokenTree::Group(group)));
+    out
+}
@@ -0,0 +1,48 @@
+use std::collections::VecDeque;
+
+use proc_macro2::{TokenStream, TokenTree};
+
+/// A token-tree iterator with unbounded lookahead.
+///
+/// The scanner in [`crate::process`] needs to look past a recognized `++`/`--`
+/// sigil pair for an optional predicate group before deciding whether a
+/// payload group follows, so a single-token `Peekable` isn't enough. This
+/// buffers tokens pulled from the underlying iterator so any of them can be
+/// peeked before being consumed.
+///
+/// Built on `proc_macro2` rather than `proc_macro` directly so the scanning
+/// logic (and its tests) can run outside an active macro expansion - the raw
+/// `proc_macro` API panics if touched from anywhere else.
+pub(crate) struct TokenIter {
+    iter: proc_macro2::token_stream::IntoIter,
+    buf: VecDeque<TokenTree>,
+}
+
+impl TokenIter {
+    pub(crate) fn new(stream: TokenStream) -> Self {
+        TokenIter {
+            iter: stream.into_iter(),
+            buf: VecDeque::new(),
+        }
+    }
+
+    fn fill(&mut self, n: usize) {
+        while self.buf.len() <= n {
+            match self.iter.next() {
+                Some(tt) => self.buf.push_back(tt),
+                None => break,
+            }
+        }
+    }
+
+    /// Looks at the `n`th not-yet-consumed token without consuming it.
+    pub(crate) fn peek(&mut self, n: usize) -> Option<&TokenTree> {
+        self.fill(n);
+        self.buf.get(n)
+    }
+
+    pub(crate) fn next(&mut self) -> Option<TokenTree> {
+        self.fill(0);
+        self.buf.pop_front()
+    }
+}
@@ -1,27 +1,66 @@
-//! # nightly - Conditional Nightly Code Inclusion
+//! # daywalker - Conditional Code Inclusion
 //!
-//! This crate enables the sharing of code between nightly and stable Rust by
-//! providing conditional inclusion syntax. It is small and lightweight. It works
-//! on a simple principle: use `++[...]` to include code only on nightly with the
-//! `nightly` feature enabled, and `--[...]` to include code only on stable
-//! without the feature. That's it!
+//! This crate enables the sharing of code between build configurations by
+//! providing conditional inclusion syntax. It is small and lightweight. It
+//! works on a simple principle: use `++[...]` to include code only when a
+//! predicate holds, and `--[...]` to include it only when the predicate does
+//! not. With no predicate given, that's `feature = "nightly"`, for backward
+//! compatibility with this crate's original nightly/stable-only purpose.
+//! That's it!
 //!
-//! When the nightly features you're using are stabilized, you can remove the
-//! conditional prefixes and remove the use of this crate.
+//! A predicate can be attached in a `(...)` group right after the sigil,
+//! e.g. `++(feature = "simd")[ ... ]` or
+//! `++(cfg(target_os = "linux"))[ ... ]`, borrowing the same `cfg`-style
+//! vocabulary (`feature = "..."`, `any`, `all`, `not`, bare idents like
+//! `unix`) rustc itself understands. Unlike the default, an explicit
+//! predicate is evaluated by a real `#[cfg(...)]` around the payload,
+//! wrapped up as a single block expression, rather than decided inside
+//! this crate - so it needs to sit somewhere a statement or a full
+//! expression is legal, not spliced into a bare-token position the way
+//! `++[const]` is in the example below, and not bare at item/module level either, since
+//! a block expression isn't itself an item. That also rules out
+//! expression-*list* positions that require every element to share one
+//! type, like a macro argument list (`vec![1, ++(feature = "x")[2]]`) or
+//! an array/tuple literal: the excluded arm's block still evaluates to
+//! `()`, which won't unify with the other elements.
+//!
+//! A bare selector can also follow the sigil directly (no `(...)`), gating
+//! on the compiler that's doing the building rather than a cfg or feature:
+//! `++since(1.75)[ ... ]`, `++before(1.80)[ ... ]`, `++nightly[ ... ]`, and
+//! `++beta[ ... ]`. Like the default predicate, these are decided
+//! immediately and so work anywhere, including bare-token positions.
+//!
+//! When both sides of a condition need to stay in sync - a nightly path and
+//! its stable fallback, say - `?~[ ... ][ ... ]` pairs them atomically: the
+//! first bracket when the (optional, same as above) condition holds, the
+//! second otherwise. Unlike two independent `++[...]`/`--[...]` statements,
+//! the two branches can't drift apart or be edited independently, and a
+//! missing second bracket is a compile error rather than silently falling
+//! through.
+//!
+//! A recognized sigil always expects well-formed syntax after it, since
+//! `++`/`--`/`?~` aren't meaningful Rust tokens on their own: a missing
+//! payload bracket, or a mismatched `+-` pair, is reported as a
+//! `compile_error!` at the sigil's own span rather than silently passed
+//! through.
+//!
+//! When the conditional code you're using is stabilized or no longer needed,
+//! you can remove the conditional prefixes and remove the use of this
+//! crate.
 //!
 //! ## Example
 //!
-//! This is the canonical example of the const trait syntax, adapted to use this
-//! crate. At the time of this writing, the const trait syntax is only available
-//! on nightly. This feature requires a syntax change, which makes it difficult
-//! to share code between nightly and stable. Using this crate, however, we can
-//! write the same codebase for both nightly and stable by using the
-//! conditional inclusion syntax.
+//! This is the canonical example of the const trait syntax, adapted to use
+//! this crate. At the time of this writing, the const trait syntax is only
+//! available on nightly. This feature requires a syntax change, which makes
+//! it difficult to share code between nightly and stable. Using this crate,
+//! however, we can write the same codebase for both nightly and stable by
+//! using the conditional inclusion syntax.
 //!
 //! ```rust
 //! #![cfg_attr(feature = "nightly", feature(const_trait_impl))]
 //!
-//! nightly::nightly! {
+//! daywalker::roam! {
 //!     pub ++[const] trait Default {
 //!         fn default() -> Self;
 //!     }
@@ -51,77 +90,139 @@
 
 extern crate proc_macro;
 
-use proc_macro::{Group, Spacing, TokenStream, TokenTree};
+mod defer;
+mod error;
+mod iter;
+mod pred;
+mod process;
+mod selector;
 
-trait Process {
-    fn process(self) -> TokenStream;
-}
+use proc_macro::TokenStream;
 
-impl Process for Group {
-    fn process(self) -> TokenStream {
-        let mut grp = Group::new(self.delimiter(), self.stream().process());
-        grp.set_span(self.span());
-        TokenTree::Group(grp).into()
-    }
-}
+use process::Process;
+use selector::Selector;
 
-impl Process for TokenStream {
-    fn process(self) -> TokenStream {
-        let mut stream = TokenStream::new();
-        let mut prev = [None, None];
-
-        for token in self {
-            match (prev[0].take(), prev[1].take(), token) {
-                // Save the first '+' or '-' if it is joint.
-                (None, None, TokenTree::Punct(p))
-                    if "+-".contains(p.as_char()) && p.spacing() == Spacing::Joint =>
-                {
-                    prev[1] = Some(TokenTree::Punct(p));
-                }
-
-                // Save the second '+' or '-' if it is alone.
-                (None, Some(TokenTree::Punct(p)), TokenTree::Punct(q))
-                    if p.as_char() == q.as_char() && q.spacing() == Spacing::Alone =>
-                {
-                    prev[0] = Some(TokenTree::Punct(p));
-                    prev[1] = Some(TokenTree::Punct(q));
-                }
-
-                // If we have a '+' or '-' pair followed by a group, conditionalize it.
-                (Some(TokenTree::Punct(p)), Some(TokenTree::Punct(_)), TokenTree::Group(grp)) => {
-                    if (p.as_char() == '+') == cfg!(feature = "nightly") {
-                        stream.extend(grp.stream());
-                    }
-                }
-
-                // Otherwise, just emit what we have and continue.
-                (p, q, tt) => {
-                    for tt in [p, q, Some(tt)] {
-                        match tt {
-                            // If we see a group, recurse into it.
-                            Some(TokenTree::Group(grp)) => stream.extend(grp.process()),
-                            Some(tt) => stream.extend(TokenStream::from(tt)),
-                            None => {}
-                        }
-                    }
-                }
-            }
-        }
-
-        stream
-    }
+/// Runs [`Process::process`] at the `proc_macro`/`proc_macro2` boundary.
+/// The scanning logic itself is written against `proc_macro2` so it (and
+/// its tests) can run outside an active macro expansion; the real entry
+/// points just convert in and back out around it.
+fn process(input: TokenStream) -> TokenStream {
+    proc_macro2::TokenStream::from(input).process().into()
 }
 
-/// Emits conditionally included code based on nightly feature availability.
+/// Emits conditionally included code based on a predicate.
+///
+/// - `++[...]` includes content only when the predicate holds
+/// - `--[...]` includes content only when the predicate does not hold
 ///
-/// - `++[...]` includes content only when `feature = "nightly"` is enabled
-/// - `--[...]` includes content only when `feature = "nightly"` is disabled
+/// The predicate defaults to `feature = "nightly"`, but an explicit one can
+/// be given in a `(...)` group right after the sigil, e.g.
+/// `++(feature = "simd")[...]` or `++(cfg(target_os = "linux"))[...]`.
 ///
 /// The macro processes the input token stream and conditionally includes or
-/// excludes bracketed content based on the feature flag. This enables writing
-/// code that uses nightly features when available but falls back to stable
+/// excludes bracketed content based on the predicate. This enables writing
+/// code that uses conditional features when available but falls back to
 /// alternatives when not.
 #[proc_macro]
+pub fn roam(input: TokenStream) -> TokenStream {
+    process(input)
+}
+
+/// Alias for [`roam!`], kept for backward compatibility with this crate's
+/// original, nightly-only name.
+#[proc_macro]
 pub fn nightly(input: TokenStream) -> TokenStream {
-    input.process()
+    process(input)
+}
+
+/// The attribute-macro form of [`roam!`]. Unlike `roam!{ ... }`, whose
+/// argument is raw, unparsed token soup, an attribute macro's annotated
+/// item has to already parse as a well-formed Rust item *before* this
+/// macro ever runs - so `++[...]`/`--[...]`/`?~[...][...]` can't be
+/// spliced directly into the item's own header or signature the way they
+/// can inside a `roam!` block; rustc rejects the item outright before the
+/// attribute gets a chance to rewrite it. What does work is a sigil nested
+/// inside an ordinary macro invocation already present in the item - that
+/// invocation's arguments are just as unparsed as `roam!`'s own, so this
+/// crate can still rewrite them first. Only the default predicate and the
+/// bare rustc selectors work here, though - they splice the payload (or
+/// nothing) directly into the argument list, unlike an explicit `(...)`
+/// predicate, whose deferred `#[cfg(...)]` block wouldn't type-check as a
+/// `vec!` element:
+///
+/// ```rust
+/// #[daywalker::roam_attr]
+/// fn limits() -> Vec<i32> {
+///     vec![1, 2, ++nightly[3]]
+/// }
+/// ```
+///
+/// Shares [`Process`](process::Process) with `roam!`/`nightly!`, so all
+/// three entry points rewrite sigils identically; only the attribute
+/// itself is stripped before the item is processed.
+#[proc_macro_attribute]
+pub fn roam_attr(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    process(item)
+}
+
+/// Emits the annotated item only when building on nightly, dropping it
+/// (leaving nothing behind) otherwise. The counterpart to [`stable_only`].
+///
+/// "Nightly" here is the same condition `++[...]`'s default predicate and
+/// the `++nightly[...]` selector each use on their own: either the
+/// `nightly` feature is enabled, or the compiling toolchain's channel
+/// (detected by `build.rs`) is nightly. This is the whole-item equivalent
+/// of bracketing a `fn`, `impl`, or `#[test]` in `++[...]`, for the common
+/// case where the bracket would otherwise have to wrap the entire item.
+#[proc_macro_attribute]
+pub fn nightly_only(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    gate_item(is_nightly(), item.into()).into()
+}
+
+/// Emits the annotated item only when *not* building on nightly, dropping
+/// it otherwise. The counterpart to [`nightly_only`]; see there for the
+/// exact condition.
+#[proc_macro_attribute]
+pub fn stable_only(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    gate_item(!is_nightly(), item.into()).into()
+}
+
+/// Whether the active channel/feature is nightly, by the same reckoning
+/// `++[...]`'s default predicate (`feature = "nightly"`) and the
+/// `++nightly[...]` selector use.
+fn is_nightly() -> bool {
+    cfg!(feature = "nightly") || Selector::Nightly.eval().unwrap_or(false)
+}
+
+/// Emits `item` when `keep` is true, or nothing at all otherwise - the
+/// shared body of [`nightly_only`]/[`stable_only`]. Takes/returns
+/// `proc_macro2::TokenStream`, like [`process`], so it can be unit-tested
+/// outside an active macro expansion.
+fn gate_item(keep: bool, item: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    if keep {
+        item
+    } else {
+        proc_macro2::TokenStream::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use proc_macro2::TokenStream;
+
+    use super::gate_item;
+
+    #[test]
+    fn gate_item_keeps_the_item_when_true() {
+        let item = TokenStream::from_str("fn foo() {}").unwrap();
+        assert_eq!(gate_item(true, item.clone()).to_string(), item.to_string());
+    }
+
+    #[test]
+    fn gate_item_drops_the_item_when_false() {
+        let item = TokenStream::from_str("fn foo() {}").unwrap();
+        assert!(gate_item(false, item).is_empty());
+    }
 }
@@ -0,0 +1,65 @@
+//! The condition recognized between a `++`/`--` sigil and its payload
+//! bracket: a bare rustc selector (`since(1.75)`, `nightly`, ...; see
+//! [`crate::selector`]), an explicit `(...)` predicate, e.g.
+//! `(feature = "simd")` or `(cfg(target_os = "linux"))`, or - with neither
+//! present - the default `feature = "nightly"`.
+
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+
+use crate::iter::TokenIter;
+use crate::selector::{self, Selector};
+
+pub(crate) enum Pred {
+    /// No explicit predicate or selector was given. Defaults to
+    /// `feature = "nightly"`, matching this crate's original, feature-only
+    /// behavior, and - unlike [`Pred::Explicit`] - is decided immediately
+    /// rather than deferred, so it keeps working for payloads spliced into
+    /// bare-token positions.
+    DefaultNightly,
+    /// An explicit `(...)` predicate, holding whatever `cfg`-meta tokens it
+    /// named (with a wrapping `cfg(...)` stripped off, if present, so
+    /// `feature = "simd"` and `cfg(feature = "simd")` are equivalent).
+    Explicit(TokenStream),
+    /// A bare rustc version/channel selector.
+    Rust(Selector),
+}
+
+impl Pred {
+    /// Recognizes and consumes whatever condition (if any) follows a
+    /// `++`/`--` sigil pair. `Err` means a selector keyword was recognized
+    /// but was malformed (e.g. `since` with no following version group) -
+    /// still consumed, since the keyword alone isn't meaningful as
+    /// ordinary code either, with the span to report it at.
+    pub(crate) fn parse(iter: &mut TokenIter) -> Result<Self, Span> {
+        if let Some(selector) = selector::parse(iter) {
+            return selector.map(Pred::Rust);
+        }
+
+        match iter.peek(0) {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
+                let tt = iter.next().unwrap();
+                let stream = match &tt {
+                    TokenTree::Group(g) => unwrap_cfg(g.stream()),
+                    _ => unreachable!(),
+                };
+                Ok(Pred::Explicit(stream))
+            }
+            _ => Ok(Pred::DefaultNightly),
+        }
+    }
+}
+
+/// Strips a single wrapping `cfg(...)`, so callers can always just look at
+/// the meta tokens rustc's own `#[cfg(...)]` would expect.
+fn unwrap_cfg(stream: TokenStream) -> TokenStream {
+    let mut iter = stream.clone().into_iter();
+    match iter.next() {
+        Some(TokenTree::Ident(id)) if id.to_string() == "cfg" => match iter.next() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis && iter.next().is_none() => {
+                g.stream()
+            }
+            _ => stream,
+        },
+        _ => stream,
+    }
+}
@@ -0,0 +1,314 @@
+use proc_macro2::{Group, Punct, Spacing, Span, TokenStream, TokenTree};
+
+use crate::defer;
+use crate::error;
+use crate::iter::TokenIter;
+use crate::pred::Pred;
+
+pub(crate) trait Process {
+    fn process(self) -> TokenStream;
+}
+
+impl Process for Group {
+    fn process(self) -> TokenStream {
+        let mut grp = Group::new(self.delimiter(), self.stream().process());
+        grp.set_span(self.span());
+        TokenTree::Group(grp).into()
+    }
+}
+
+impl Process for TokenStream {
+    fn process(self) -> TokenStream {
+        let mut stream = TokenStream::new();
+        let mut iter = TokenIter::new(self);
+
+        while let Some(token) = iter.next() {
+            let p = match &token {
+                TokenTree::Punct(p) => p.clone(),
+                _ => {
+                    stream.extend(flatten(token));
+                    continue;
+                }
+            };
+
+            if is_sigil(&p) {
+                match iter.peek(0) {
+                    Some(TokenTree::Punct(q)) if q.as_char() == p.as_char() && q.spacing() == Spacing::Alone => {
+                        iter.next();
+                        process_conditional(&mut stream, &mut iter, p);
+                        continue;
+                    }
+                    // The other sigil half showed up instead, e.g. `+-`: the
+                    // user almost certainly meant `++` or `--`, so this is a
+                    // typo rather than ordinary code to pass through.
+                    Some(TokenTree::Punct(q)) if "+-".contains(q.as_char()) && q.as_char() != p.as_char() => {
+                        iter.next();
+                        stream.extend(error::emit(
+                            p.span(),
+                            "mismatched conditional sigil: `+`/`-` must be doubled, e.g. `++` or `--`",
+                        ));
+                        continue;
+                    }
+                    _ => {}
+                }
+            } else if is_branch_lead(&p) {
+                let second_matches = matches!(
+                    iter.peek(0),
+                    Some(TokenTree::Punct(q)) if q.as_char() == '~' && q.spacing() == Spacing::Alone
+                );
+                if second_matches {
+                    iter.next();
+                    process_branch(&mut stream, &mut iter, p.span());
+                    continue;
+                }
+            }
+
+            stream.extend(flatten(token));
+        }
+
+        stream
+    }
+}
+
+/// Handles a recognized `++`/`--` sigil pair, spanned at `first`. A missing
+/// payload group is a compile error at that span rather than a silent
+/// passthrough, since this crate is the only legitimate user of a joint
+/// `++`/`--` pair in a token stream - anything else following one is almost
+/// certainly a typo.
+fn process_conditional(stream: &mut TokenStream, iter: &mut TokenIter, first: Punct) {
+    // Whatever condition (selector or explicit predicate) follows the
+    // sigil, if any.
+    let pred = match Pred::parse(iter) {
+        Ok(pred) => pred,
+        Err(span) => {
+            stream.extend(error::emit(
+                span,
+                "`since`/`before` require a version in parens, e.g. `since(1.75)`",
+            ));
+            return;
+        }
+    };
+
+    let payload = match iter.peek(0) {
+        Some(TokenTree::Group(_)) => match iter.next() {
+            Some(TokenTree::Group(payload)) => payload,
+            _ => unreachable!(),
+        },
+        _ => {
+            stream.extend(error::emit(
+                first.span(),
+                "expected a bracketed payload after this sigil, e.g. `++[...]`",
+            ));
+            return;
+        }
+    };
+
+    let positive = first.as_char() == '+';
+    let payload = payload.stream().process();
+
+    match pred {
+        // No proc-macro can see the consuming crate's real cfg
+        // state, but the original `feature = "nightly"` default
+        // exploits Cargo's feature unification to make `cfg!` here
+        // reflect it anyway - the only option for payloads spliced
+        // into bare-token positions like `++[const]`.
+        Pred::DefaultNightly => {
+            if positive == cfg!(feature = "nightly") {
+                stream.extend(payload);
+            }
+        }
+        // An explicit predicate names real target cfg state this
+        // proc-macro can't observe; defer to a genuine `#[cfg(...)]`
+        // so rustc decides for real.
+        Pred::Explicit(meta) => {
+            stream.extend(defer::gate(positive, meta, payload));
+        }
+        // Selectors reflect the compiling toolchain itself, a
+        // genuine host property, so they're decided immediately. An
+        // unresolvable selector (e.g. `DAYWALKER_MINOR` missing) is
+        // `None`, which matches neither sign - so it conservatively
+        // excludes the payload for `++since(...)` and `--since(...)`
+        // alike, rather than falling out of a plain boolean XNOR.
+        Pred::Rust(selector) => {
+            if selector.eval() == Some(positive) {
+                stream.extend(payload);
+            }
+        }
+    }
+}
+
+/// Handles a recognized `?~` then/else sigil pair, spanned at `span`: emits
+/// the first bracket group when the (optional) condition holds, the second
+/// otherwise. Like [`process_conditional`], a missing bracket - either one -
+/// is a compile error at `span` rather than a silent passthrough.
+fn process_branch(stream: &mut TokenStream, iter: &mut TokenIter, span: Span) {
+    let pred = match Pred::parse(iter) {
+        Ok(pred) => pred,
+        Err(selector_span) => {
+            stream.extend(error::emit(
+                selector_span,
+                "`since`/`before` require a version in parens, e.g. `since(1.75)`",
+            ));
+            return;
+        }
+    };
+
+    let then_group = match iter.peek(0) {
+        Some(TokenTree::Group(_)) => match iter.next() {
+            Some(TokenTree::Group(g)) => g,
+            _ => unreachable!(),
+        },
+        _ => {
+            stream.extend(error::emit(
+                span,
+                "expected two bracketed branches after this sigil: `?~[then][else]`",
+            ));
+            return;
+        }
+    };
+
+    let else_group = match iter.peek(0) {
+        Some(TokenTree::Group(_)) => match iter.next() {
+            Some(TokenTree::Group(g)) => g,
+            _ => unreachable!(),
+        },
+        _ => {
+            stream.extend(error::emit(
+                span,
+                "`?~` requires two bracketed branches: `?~[then][else]`",
+            ));
+            return;
+        }
+    };
+
+    let then_payload = then_group.stream().process();
+    let else_payload = else_group.stream().process();
+
+    match pred {
+        Pred::DefaultNightly => {
+            stream.extend(if cfg!(feature = "nightly") {
+                then_payload
+            } else {
+                else_payload
+            });
+        }
+        Pred::Explicit(meta) => {
+            stream.extend(defer::branch(meta, then_payload, else_payload));
+        }
+        Pred::Rust(selector) => {
+            // An unresolvable selector takes the `else` branch. Unlike
+            // the plain `++`/`--` case in `process_conditional`, which
+            // drops the payload for both signs, one arm is always emitted.
+            stream.extend(if selector.eval().unwrap_or(false) {
+                then_payload
+            } else {
+                else_payload
+            });
+        }
+    }
+}
+
+/// Whether `p` could be the first half of a joint `++`/`--` pair.
+fn is_sigil(p: &Punct) -> bool {
+    "+-".contains(p.as_char()) && p.spacing() == Spacing::Joint
+}
+
+/// Whether `p` could be the first half of a joint `?~` then/else pair.
+fn is_branch_lead(p: &Punct) -> bool {
+    p.as_char() == '?' && p.spacing() == Spacing::Joint
+}
+
+/// Emits a single token tree, recursing into groups so nested sigils are
+/// still processed.
+fn flatten(tt: TokenTree) -> TokenStream {
+    match tt {
+        TokenTree::Group(grp) => grp.process(),
+        tt => TokenStream::from(tt),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    // `proc_macro2`, not `proc_macro` - the real `proc_macro` API panics
+    // ("procedural macro API is used outside of a procedural macro") the
+    // moment it's touched from an ordinary `#[test]`, since it only works
+    // inside an active macro expansion. `proc_macro2` mirrors the same API
+    // but falls back to a standalone implementation outside one, which is
+    // exactly what lets `Process::process` be exercised here at all.
+    use proc_macro2::{Delimiter, TokenStream, TokenTree};
+
+    use super::Process;
+
+    fn process(src: &str) -> TokenStream {
+        TokenStream::from_str(src).unwrap().process()
+    }
+
+    #[test]
+    fn branch_picks_the_else_group_by_default() {
+        // No predicate, and the `nightly` feature isn't enabled for this
+        // build, so the default condition is false.
+        let out = process("?~[1][2]").to_string();
+        assert!(out.contains('2'));
+        assert!(!out.contains('1'));
+    }
+
+    #[test]
+    fn branch_errors_on_a_single_group() {
+        let out = process("?~[1]").to_string();
+        assert!(out.contains("compile_error"));
+        assert!(out.contains("two bracketed branches"));
+    }
+
+    #[test]
+    fn branch_defers_an_explicit_predicate_as_one_block() {
+        // An explicit predicate can't be decided here, so both arms have to
+        // be emitted behind a real `#[cfg(...)]` - as a single `{ ... }`
+        // block expression, not a bare item/macro-call sequence.
+        let out = process(r#"?~(feature = "impossible")[1][2]"#);
+        let mut trees = out.into_iter();
+        match trees.next() {
+            Some(TokenTree::Group(g)) => assert_eq!(g.delimiter(), Delimiter::Brace),
+            other => panic!("expected a single brace group, got {other:?}"),
+        }
+        assert!(trees.next().is_none());
+    }
+
+    #[test]
+    fn mismatched_sigil_errors() {
+        let out = process("+-[1]").to_string();
+        assert!(out.contains("compile_error"));
+        assert!(out.contains("mismatched"));
+    }
+
+    #[test]
+    fn missing_payload_errors() {
+        let out = process("++ 1").to_string();
+        assert!(out.contains("compile_error"));
+        assert!(out.contains("expected a bracketed payload"));
+    }
+
+    #[test]
+    fn malformed_selector_is_consumed_and_reported_precisely() {
+        // Regression test: `since`/`before` without a `(...)` version used
+        // to be left unconsumed, so the real bracket right after it was
+        // misreported as missing, and the stray keyword went on to produce
+        // an unrelated error once flattened through as ordinary code.
+        let out = process("++since[1] --since[2]").to_string();
+        assert_eq!(out.matches("compile_error").count(), 2);
+        assert!(!out.contains("expected a bracketed payload"));
+    }
+
+    #[test]
+    fn unresolved_version_selector_conservatively_excludes_both_signs() {
+        // Regression test: an unparseable version literal makes
+        // `Selector::eval` return `None`, which used to collapse to
+        // `false` and so let `--since(...)` wrongly include its payload.
+        // `None` must exclude the payload for both `++since(...)` and
+        // `--since(...)` alike.
+        let out = process("++since(abc)[1] --since(abc)[2]").to_string();
+        assert!(!out.contains('1'));
+        assert!(!out.contains('2'));
+    }
+}
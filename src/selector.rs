@@ -0,0 +1,116 @@
+//! The `since(1.75)`, `before(1.80)`, `nightly`, and `beta` selectors that
+//! may follow a `++`/`--` sigil directly (no enclosing `(...)`), gating on
+//! the compiler itself rather than a cfg or feature.
+//!
+//! `build.rs` bakes the toolchain's minor version and channel in as the
+//! `DAYWALKER_MINOR`/`DAYWALKER_CHANNEL` environment variables at this
+//! crate's own compile time. Both are genuine host properties - the same
+//! `rustc` compiles this proc-macro and whatever depends on it - so, like
+//! the default `feature = "nightly"` predicate, these selectors are decided
+//! immediately rather than deferred the way [`crate::pred::Pred::Explicit`]
+//! is.
+
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+
+use crate::iter::TokenIter;
+
+pub(crate) enum Selector {
+    Since(Option<u32>),
+    Before(Option<u32>),
+    Nightly,
+    Beta,
+}
+
+impl Selector {
+    /// Evaluates the selector against this toolchain, or `None` if that
+    /// can't be determined (an unresolvable `DAYWALKER_MINOR`, or an
+    /// unparseable version literal). Unlike a plain `bool`, the unknown
+    /// case has to stay distinct from "false": `process_conditional` folds
+    /// it into "exclude the payload" for *both* `++since(...)` and
+    /// `--since(...)`, not just the `++` side - collapsing it to `false`
+    /// here would make `--since(...)`/`--before(...)` wrongly include
+    /// their payload whenever the version can't be read.
+    pub(crate) fn eval(&self) -> Option<bool> {
+        match self {
+            Selector::Since(Some(min)) => Some(minor()? >= *min),
+            Selector::Before(Some(max)) => Some(minor()? < *max),
+            // An unparseable version literal can never resolve.
+            Selector::Since(None) | Selector::Before(None) => None,
+            Selector::Nightly => Some(channel() == "nightly"),
+            Selector::Beta => Some(channel() == "beta"),
+        }
+    }
+}
+
+/// Recognizes a selector keyword at the front of `iter`, consuming it (and,
+/// for `since`/`before`, the following `(...)` version group). Returns
+/// `None`, consuming nothing, if `iter` doesn't start with one of these
+/// keywords. `since`/`before` not immediately followed by a parenthesized
+/// group is still consumed, but reported back as `Err` with the keyword's
+/// span - since the keyword alone isn't valid surrounding code either, the
+/// caller's only sane options are "this is our malformed selector" or
+/// nothing, never "leave it for something else to parse".
+pub(crate) fn parse(iter: &mut TokenIter) -> Option<Result<Selector, Span>> {
+    let name = match iter.peek(0) {
+        Some(TokenTree::Ident(id)) => id.to_string(),
+        _ => return None,
+    };
+
+    match name.as_str() {
+        "nightly" => {
+            iter.next();
+            Some(Ok(Selector::Nightly))
+        }
+        "beta" => {
+            iter.next();
+            Some(Ok(Selector::Beta))
+        }
+        "since" | "before" => {
+            let has_group = matches!(
+                iter.peek(1),
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis
+            );
+
+            let name_tt = iter.next().unwrap();
+            let span = match &name_tt {
+                TokenTree::Ident(id) => id.span(),
+                _ => unreachable!(),
+            };
+            if !has_group {
+                return Some(Err(span));
+            }
+
+            let group_tt = iter.next().unwrap();
+            let version = match &group_tt {
+                TokenTree::Group(g) => parse_minor(g.stream()),
+                _ => unreachable!(),
+            };
+
+            let selector = if name == "since" {
+                Selector::Since(version)
+            } else {
+                Selector::Before(version)
+            };
+            Some(Ok(selector))
+        }
+        _ => None,
+    }
+}
+
+/// Pulls the minor component out of a version literal like `1.75`.
+fn parse_minor(stream: TokenStream) -> Option<u32> {
+    let mut iter = stream.into_iter();
+    let text = match iter.next()? {
+        TokenTree::Literal(lit) => lit.to_string(),
+        _ => return None,
+    };
+    text.split('.').nth(1)?.parse().ok()
+}
+
+fn minor() -> Option<u32> {
+    option_env!("DAYWALKER_MINOR")?.parse().ok()
+}
+
+fn channel() -> &'static str {
+    option_env!("DAYWALKER_CHANNEL").unwrap_or("stable")
+}